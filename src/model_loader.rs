@@ -0,0 +1,511 @@
+//! ZON zone and model loading for the ROSE client.
+//!
+//! Parses `.zon` zone files and their tile tables into renderer-ready
+//! geometry. Each loading stage (`load_zon`/`load_zon_parallel`, per-tile
+//! processing) is wrapped in a `tracing` span carrying the file path and
+//! tile index as fields, which a profiler such as `tracing-chrome` can
+//! consume directly for per-tile timing.
+//!
+//! This crate's `release_max_level_debug` / `release_max_level_trace`
+//! features forward to `tracing`'s same-named features, which set the most
+//! verbose level compiled into release builds. With
+//! `release_max_level_debug`, `trace!` events compile out of release builds;
+//! with `release_max_level_trace`, nothing is compiled out. Neither feature
+//! compiles out `debug!` events, and with neither enabled every level stays
+//! compiled in and is filtered at runtime.
+
+use std::path::Path;
+
+use once_cell::sync::OnceCell;
+use rayon::prelude::*;
+use thiserror::Error;
+use tracing::{debug, info, instrument};
+
+const ZON_MAGIC: &[u8; 4] = b"ZON\0";
+/// Byte offset at which the flat tile table begins (magic + version + tile count).
+const TILE_TABLE_OFFSET: usize = 12;
+/// Size in bytes of one tile table entry (`texture_id` + `texture_id_top` + `rotation`).
+const TILE_ENTRY_SIZE: usize = 9;
+
+/// Everything that can go wrong loading a `.zon` zone or its tiles.
+///
+/// Parsing untrusted map files should never panic the host application, so
+/// every fallible step in this module returns one of these variants instead
+/// of using `unwrap`/`expect` or silently skipping the offending data.
+#[derive(Debug, Error)]
+pub enum RoseLoadError {
+    #[error("failed to read zone file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("file is truncated at byte offset {offset}")]
+    TruncatedFile { offset: usize },
+
+    #[error("file does not start with the expected ZON magic bytes")]
+    BadMagic,
+
+    #[error("unsupported ZON version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("tile {tile} is out of range for a tile table of {count} tiles")]
+    TileIndexOutOfRange { tile: usize, count: usize },
+}
+
+/// A single tile entry as stored in a `.zon` file's flat tile table.
+///
+/// Each tile samples two stacked texture layers — `texture_id` for the
+/// bottom layer and `texture_id_top` for the blended-in top layer — both
+/// using the same quad, so both need the same `rotation`-derived UVs for
+/// the layers to stay aligned with each other.
+#[derive(Debug, Clone, Copy)]
+pub struct ZonTile {
+    pub tile_index: u32,
+    pub texture_id: u32,
+    pub texture_id_top: u32,
+    pub rotation: u8,
+}
+
+/// Base unit-quad UVs before any per-tile orientation is applied, in
+/// `(bottom-left, bottom-right, top-right, top-left)` winding order.
+const BASE_TILE_UVS: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+
+/// Per-tile renderer geometry: just the aligned UVs for now.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileMesh {
+    pub uvs: [[f32; 2]; 4],
+}
+
+/// Compute the corner UVs shared by a tile's bottom (`texture_id`) and top
+/// (`texture_id_top`) layers, oriented according to the tile's rotation
+/// byte so the two layers stay aligned with each other and with neighboring
+/// tiles across the tilemap.
+///
+/// Rotation codes, matching the ZON tile format:
+/// - `0`: none
+/// - `1`: horizontal flip (`u -> 1 - u`)
+/// - `2`: vertical flip (`v -> 1 - v`)
+/// - `3`: flip both
+/// - `4`, `5`, `6`: rotate 90/180/270 degrees (cyclic permutation of corners)
+///
+/// Any other value is treated as `0` (no orientation).
+pub fn align_tile_uvs(tile: &ZonTile) -> [[f32; 2]; 4] {
+    let hflip = |uvs: [[f32; 2]; 4]| uvs.map(|[u, v]| [1.0 - u, v]);
+    let vflip = |uvs: [[f32; 2]; 4]| uvs.map(|[u, v]| [u, 1.0 - v]);
+    let rotate = |uvs: [[f32; 2]; 4], steps: usize| {
+        let mut rotated = uvs;
+        rotated.rotate_left(steps);
+        rotated
+    };
+
+    match tile.rotation {
+        1 => hflip(BASE_TILE_UVS),
+        2 => vflip(BASE_TILE_UVS),
+        3 => vflip(hflip(BASE_TILE_UVS)),
+        4 => rotate(BASE_TILE_UVS, 1),
+        5 => rotate(BASE_TILE_UVS, 2),
+        6 => rotate(BASE_TILE_UVS, 3),
+        _ => BASE_TILE_UVS,
+    }
+}
+
+/// Parsed zone data: the flat tile table plus per-tile geometry.
+///
+/// Per-tile meshes are lazy: each one sits behind a [`OnceCell`] and is only
+/// computed the first time it's asked for via [`ZonData::tile_mesh`]. A
+/// consumer that only needs a handful of tiles out of a large block map
+/// never pays for the rest. [`load_zon`] and [`load_zon_parallel`] force
+/// every cell up front; constructing a `ZonData` any other way leaves them
+/// unmaterialized.
+#[derive(Debug, Default)]
+pub struct ZonData {
+    pub tiles: Vec<ZonTile>,
+    tile_meshes: Vec<OnceCell<TileMesh>>,
+}
+
+impl ZonData {
+    fn from_tiles(tiles: Vec<ZonTile>) -> Self {
+        let tile_meshes = tiles.iter().map(|_| OnceCell::new()).collect();
+        ZonData { tiles, tile_meshes }
+    }
+
+    /// Look up a tile by index, returning a precise error instead of
+    /// panicking when the index is out of range.
+    pub fn tile(&self, tile_index: usize) -> Result<&ZonTile, RoseLoadError> {
+        self.tiles
+            .get(tile_index)
+            .ok_or(RoseLoadError::TileIndexOutOfRange {
+                tile: tile_index,
+                count: self.tiles.len(),
+            })
+    }
+
+    /// Get a tile's aligned mesh, computing and caching it on first access.
+    pub fn tile_mesh(&self, tile_index: usize) -> Result<&TileMesh, RoseLoadError> {
+        let tile = self.tile(tile_index)?;
+        self.tile_meshes[tile_index].get_or_try_init(|| process_tile(tile_index, tile))
+    }
+}
+
+/// Load a `.zon` zone file from disk, materializing every tile's mesh
+/// sequentially before returning.
+///
+/// This is the simple, single-threaded entry point: reach for it on small
+/// zones or when deterministic ordering matters (e.g. a debug build being
+/// profiled tile-by-tile). For a large zone, prefer [`load_zon_parallel`].
+#[instrument(skip_all, fields(path = %path.as_ref().display()))]
+pub fn load_zon(path: impl AsRef<Path>) -> Result<ZonData, RoseLoadError> {
+    let path = path.as_ref();
+    debug!("parsing ZON file");
+
+    let bytes = std::fs::read(path)?;
+    let zon_data = parse_zon_file(&bytes)?;
+
+    info!(tile_count = zon_data.tiles.len(), "loaded ZON zone");
+
+    for tile_index in 0..zon_data.tiles.len() {
+        zon_data.tile_mesh(tile_index)?;
+    }
+
+    Ok(zon_data)
+}
+
+/// Load a `.zon` zone file, computing every tile's mesh in parallel across
+/// the Rayon thread pool instead of one at a time.
+///
+/// Tiles don't reference each other, so there's no ordering to preserve;
+/// prefer this over [`load_zon`] once a zone's tile count makes the
+/// sequential loop show up in a profile.
+///
+/// # Threading
+/// The calling thread blocks until every tile has been processed — this
+/// function hands work to Rayon's pool, not to an async executor. Run it
+/// from a blocking context (e.g. `tokio::task::spawn_blocking`) rather than
+/// an async task, or the zone load will run on and block that task's
+/// executor thread.
+#[instrument(skip_all, fields(path = %path.as_ref().display()))]
+pub fn load_zon_parallel(path: impl AsRef<Path>) -> Result<ZonData, RoseLoadError> {
+    let path = path.as_ref();
+    debug!("parsing ZON file");
+
+    let bytes = std::fs::read(path)?;
+    let zon_data = parse_zon_file(&bytes)?;
+
+    info!(tile_count = zon_data.tiles.len(), "loaded ZON zone (parallel)");
+
+    let meshes: Vec<TileMesh> = zon_data
+        .tiles
+        .par_iter()
+        .enumerate()
+        .map(|(tile_index, tile)| process_tile(tile_index, tile))
+        .collect::<Result<_, _>>()?;
+
+    for (cell, mesh) in zon_data.tile_meshes.iter().zip(meshes) {
+        // Cells are freshly allocated and unset, so this cannot fail.
+        let _ = cell.set(mesh);
+    }
+
+    Ok(zon_data)
+}
+
+#[instrument(skip(bytes))]
+fn parse_zon_file(bytes: &[u8]) -> Result<ZonData, RoseLoadError> {
+    let magic = bytes
+        .get(0..4)
+        .ok_or(RoseLoadError::TruncatedFile { offset: 0 })?;
+    if magic != ZON_MAGIC {
+        return Err(RoseLoadError::BadMagic);
+    }
+
+    let version = bytes
+        .get(4..8)
+        .ok_or(RoseLoadError::TruncatedFile { offset: 4 })
+        .map(|b| u32::from_le_bytes(b.try_into().expect("slice is 4 bytes")))?;
+    if version != 1 {
+        return Err(RoseLoadError::UnsupportedVersion(version));
+    }
+
+    let tile_count = bytes
+        .get(8..12)
+        .ok_or(RoseLoadError::TruncatedFile { offset: 8 })
+        .map(|b| u32::from_le_bytes(b.try_into().expect("slice is 4 bytes")))? as usize;
+
+    // Validate the claimed tile count against the bytes actually available
+    // before trusting it as a `Vec` capacity — otherwise a tiny file with a
+    // near-`u32::MAX` tile count triggers a multi-gigabyte allocation that
+    // aborts the process instead of returning a `RoseLoadError`.
+    let available_tiles = (bytes.len() - TILE_TABLE_OFFSET) / TILE_ENTRY_SIZE;
+    if tile_count > available_tiles {
+        return Err(RoseLoadError::TruncatedFile {
+            offset: TILE_TABLE_OFFSET,
+        });
+    }
+
+    let mut tiles = Vec::with_capacity(tile_count);
+    let mut offset = TILE_TABLE_OFFSET;
+    for tile_index in 0..tile_count {
+        let entry = bytes
+            .get(offset..offset + TILE_ENTRY_SIZE)
+            .ok_or(RoseLoadError::TruncatedFile { offset })?;
+        tiles.push(ZonTile {
+            tile_index: tile_index as u32,
+            texture_id: u32::from_le_bytes(entry[0..4].try_into().expect("slice is 4 bytes")),
+            texture_id_top: u32::from_le_bytes(entry[4..8].try_into().expect("slice is 4 bytes")),
+            rotation: entry[8],
+        });
+        offset += TILE_ENTRY_SIZE;
+    }
+
+    Ok(ZonData::from_tiles(tiles))
+}
+
+/// Process a single tile: align its texture UVs and build its mesh.
+#[instrument(skip(tile))]
+fn process_tile(tile_index: usize, tile: &ZonTile) -> Result<TileMesh, RoseLoadError> {
+    let uvs = align_tile_uvs(tile);
+    debug!(tile_index, rotation = tile.rotation, "aligned tile texture UVs");
+    Ok(TileMesh { uvs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile_with_rotation(rotation: u8) -> ZonTile {
+        ZonTile {
+            tile_index: 0,
+            texture_id: 0,
+            texture_id_top: 0,
+            rotation,
+        }
+    }
+
+    /// Build a well-formed `.zon` byte buffer: magic + version(1) + a tile
+    /// table of `tiles.len()` entries, each `(texture_id, texture_id_top,
+    /// rotation)`.
+    fn zon_bytes(tiles: &[(u32, u32, u8)]) -> Vec<u8> {
+        let mut bytes = ZON_MAGIC.to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&(tiles.len() as u32).to_le_bytes());
+        for &(texture_id, texture_id_top, rotation) in tiles {
+            bytes.extend_from_slice(&texture_id.to_le_bytes());
+            bytes.extend_from_slice(&texture_id_top.to_le_bytes());
+            bytes.push(rotation);
+        }
+        bytes
+    }
+
+    #[test]
+    fn parse_reads_both_texture_layers() {
+        let bytes = zon_bytes(&[(7, 9, 4)]);
+        let zon_data = parse_zon_file(&bytes).expect("well-formed buffer parses");
+        let tile = zon_data.tile(0).expect("tile 0 exists");
+        assert_eq!(tile.texture_id, 7);
+        assert_eq!(tile.texture_id_top, 9);
+        assert_eq!(tile.rotation, 4);
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        let mut bytes = zon_bytes(&[]);
+        bytes[0] = b'X';
+        assert!(matches!(
+            parse_zon_file(&bytes),
+            Err(RoseLoadError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn unsupported_version_is_rejected() {
+        let mut bytes = zon_bytes(&[]);
+        bytes[4..8].copy_from_slice(&2u32.to_le_bytes());
+        assert!(matches!(
+            parse_zon_file(&bytes),
+            Err(RoseLoadError::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn truncated_magic_reports_offset_zero() {
+        let bytes = &ZON_MAGIC[..2];
+        assert!(matches!(
+            parse_zon_file(bytes),
+            Err(RoseLoadError::TruncatedFile { offset: 0 })
+        ));
+    }
+
+    #[test]
+    fn truncated_version_reports_offset_four() {
+        let mut bytes = ZON_MAGIC.to_vec();
+        bytes.extend_from_slice(&[0, 0]);
+        assert!(matches!(
+            parse_zon_file(&bytes),
+            Err(RoseLoadError::TruncatedFile { offset: 4 })
+        ));
+    }
+
+    #[test]
+    fn truncated_tile_count_reports_offset_eight() {
+        let mut bytes = ZON_MAGIC.to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&[0, 0]);
+        assert!(matches!(
+            parse_zon_file(&bytes),
+            Err(RoseLoadError::TruncatedFile { offset: 8 })
+        ));
+    }
+
+    #[test]
+    fn truncated_tile_entry_reports_its_offset() {
+        // Header claims one tile but the buffer ends right after it.
+        let mut bytes = ZON_MAGIC.to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        assert!(matches!(
+            parse_zon_file(&bytes),
+            Err(RoseLoadError::TruncatedFile { offset: 12 })
+        ));
+    }
+
+    #[test]
+    fn huge_tile_count_is_rejected_before_allocating() {
+        // A tiny file claiming a near-u32::MAX tile count must be rejected
+        // by validating against the buffer's actual remaining length,
+        // rather than trusted as a `Vec::with_capacity` argument.
+        let mut bytes = ZON_MAGIC.to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            parse_zon_file(&bytes),
+            Err(RoseLoadError::TruncatedFile { offset: 12 })
+        ));
+    }
+
+    #[test]
+    fn tile_index_out_of_range_is_reported() {
+        let bytes = zon_bytes(&[(0, 0, 0)]);
+        let zon_data = parse_zon_file(&bytes).expect("well-formed buffer parses");
+        assert!(matches!(
+            zon_data.tile(5),
+            Err(RoseLoadError::TileIndexOutOfRange { tile: 5, count: 1 })
+        ));
+    }
+
+    #[test]
+    fn rotation_0_is_identity() {
+        assert_eq!(align_tile_uvs(&tile_with_rotation(0)), BASE_TILE_UVS);
+    }
+
+    #[test]
+    fn rotation_1_flips_horizontally() {
+        assert_eq!(
+            align_tile_uvs(&tile_with_rotation(1)),
+            [[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn rotation_2_flips_vertically() {
+        assert_eq!(
+            align_tile_uvs(&tile_with_rotation(2)),
+            [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn rotation_3_flips_both() {
+        assert_eq!(
+            align_tile_uvs(&tile_with_rotation(3)),
+            [[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn rotation_4_rotates_90_degrees() {
+        assert_eq!(
+            align_tile_uvs(&tile_with_rotation(4)),
+            [[1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn rotation_5_rotates_180_degrees() {
+        assert_eq!(
+            align_tile_uvs(&tile_with_rotation(5)),
+            [[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn rotation_6_rotates_270_degrees() {
+        assert_eq!(
+            align_tile_uvs(&tile_with_rotation(6)),
+            [[0.0, 1.0], [0.0, 0.0], [1.0, 0.0], [1.0, 1.0]]
+        );
+    }
+
+    fn write_temp_zon(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "io_rose_test_{}_{}_{}.zon",
+            name,
+            std::process::id(),
+            bytes.len()
+        ));
+        std::fs::write(&path, bytes).expect("write temp zon file");
+        path
+    }
+
+    #[test]
+    fn tile_mesh_is_computed_lazily_on_first_access() {
+        let bytes = zon_bytes(&[(1, 0, 0), (2, 0, 1), (3, 0, 4)]);
+        let zon_data = parse_zon_file(&bytes).expect("well-formed buffer parses");
+
+        assert!(
+            zon_data.tile_meshes.iter().all(|cell| cell.get().is_none()),
+            "parse_zon_file must not materialize any tile mesh"
+        );
+
+        let mesh = *zon_data.tile_mesh(1).expect("tile 1 exists");
+
+        assert!(zon_data.tile_meshes[0].get().is_none(), "tile 0 untouched");
+        assert!(zon_data.tile_meshes[2].get().is_none(), "tile 2 untouched");
+        assert_eq!(zon_data.tile_meshes[1].get(), Some(&mesh));
+
+        // Second access returns the same cached mesh rather than recomputing.
+        assert_eq!(zon_data.tile_mesh(1).expect("tile 1 exists"), &mesh);
+    }
+
+    #[test]
+    fn load_zon_and_load_zon_parallel_agree() {
+        let bytes = zon_bytes(&[(1, 10, 0), (2, 20, 1), (3, 30, 4), (4, 40, 6)]);
+        let path = write_temp_zon("agree", &bytes);
+
+        let sequential = load_zon(&path).expect("load_zon succeeds");
+        let parallel = load_zon_parallel(&path).expect("load_zon_parallel succeeds");
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(sequential.tiles.len(), parallel.tiles.len());
+        for tile_index in 0..sequential.tiles.len() {
+            let sequential_tile = sequential.tile(tile_index).unwrap();
+            let parallel_tile = parallel.tile(tile_index).unwrap();
+            assert_eq!(sequential_tile.texture_id, parallel_tile.texture_id);
+            assert_eq!(sequential_tile.texture_id_top, parallel_tile.texture_id_top);
+            assert_eq!(sequential_tile.rotation, parallel_tile.rotation);
+
+            assert_eq!(
+                sequential.tile_mesh(tile_index).unwrap(),
+                parallel.tile_mesh(tile_index).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn load_zon_parallel_materializes_every_tile() {
+        let bytes = zon_bytes(&[(0, 0, 0), (0, 0, 2), (0, 0, 5)]);
+        let path = write_temp_zon("parallel_eager", &bytes);
+
+        let zon_data = load_zon_parallel(&path).expect("load_zon_parallel succeeds");
+        std::fs::remove_file(&path).ok();
+
+        assert!(zon_data.tile_meshes.iter().all(|cell| cell.get().is_some()));
+    }
+}